@@ -0,0 +1,130 @@
+use actix_web::dev::Payload;
+use actix_web::web::Data;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use chrono::Utc;
+use futures_util::future::{ready, Ready};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::error_log;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Role {
+    admin,
+    user,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: u32,
+    pub role: Role,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// Looks up the role a freshly authenticated user should be issued, based on
+/// the admin ids listed in `Config`.
+pub fn role_for(config: &Config, user_id: u32) -> Role {
+    if config.admin_ids.contains(&user_id) {
+        Role::admin
+    } else {
+        Role::user
+    }
+}
+
+/// Signs a `{ sub, role, iat, exp }` JWT with HS256 using `config.jwt_secret`.
+pub fn sign_token(config: &Config, user_id: u32, role: Role) -> String {
+    let iat = Utc::now().timestamp();
+    let claims = Claims {
+        sub: user_id,
+        role,
+        iat,
+        exp: iat + config.jwt_expiry_secs,
+    };
+    encode(
+        &Header::new(Algorithm::HS256),
+        &claims,
+        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    )
+    .expect("Failed to sign JWT.")
+}
+
+/// Validates the signature and `exp` claim of `token`.
+pub fn verify_token(config: &Config, token: &str) -> Result<Claims, AuthError> {
+    decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
+        &Validation::new(Algorithm::HS256),
+    )
+    .map(|data| data.claims)
+    .map_err(|_| AuthError::InvalidToken)
+}
+
+/// Reason an `AuthUser` extraction failed. Kept distinct from the handler-body
+/// `error_log` responses since this runs before a handler body exists.
+#[derive(Debug)]
+pub enum AuthError {
+    MissingHeader,
+    InvalidToken,
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "authentication failed")
+    }
+}
+
+impl actix_web::ResponseError for AuthError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            AuthError::MissingHeader => {
+                error_log::INVALID_ARGUMENT::webmsg("Missing Authorization header.")
+            }
+            AuthError::InvalidToken => {
+                error_log::INVALID_ARGUMENT::webmsg("Invalid or expired token.")
+            }
+        }
+    }
+}
+
+/// The authenticated principal for a request, extracted from the
+/// `Authorization: Bearer` header by actix before the handler runs.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub user_id: u32,
+    pub role: Role,
+}
+
+impl AuthUser {
+    pub fn is_admin(&self) -> bool {
+        matches!(self.role, Role::admin)
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = AuthError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let config = req
+            .app_data::<Data<Config>>()
+            .expect("Config not configured.");
+
+        let token = match req
+            .headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+        {
+            Some(token) => token,
+            None => return ready(Err(AuthError::MissingHeader)),
+        };
+
+        ready(verify_token(config, token).map(|claims| AuthUser {
+            user_id: claims.sub,
+            role: claims.role,
+        }))
+    }
+}
@@ -1,5 +1,5 @@
 use actix_web::web::Data;
-use actix_web::{get, post, put, web, HttpRequest, HttpResponse};
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use serde::{Deserialize, Serialize};
@@ -9,13 +9,18 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+use crate::auth::{self, AuthUser};
 use crate::config::{self, Config, Ids};
 use crate::contests;
 use crate::error_log;
+use crate::job_state::JobState;
+use crate::query;
+use crate::queue::{JobQueue, RunnableJob};
 use crate::runner;
+use crate::storage::Storage;
 use crate::users;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PostJob {
     pub source_code: String,
     pub language: String,
@@ -28,6 +33,9 @@ pub struct PostJob {
 pub struct PostUser {
     pub id: Option<u32>,
     pub name: String,
+    /// Plaintext password; required when creating a user, optional when
+    /// updating one (omit to leave the stored hash unchanged).
+    pub password: Option<String>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -50,7 +58,7 @@ pub struct JobsFilter {
     pub language: Option<String>,
     pub from: Option<String>,
     pub to: Option<String>,
-    pub state: Option<String>,
+    pub state: Option<JobState>,
     pub result: Option<String>,
 }
 
@@ -84,14 +92,50 @@ pub struct SerdeRankFilter {
     pub tie_breaker: Option<TieBreaker>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct PostLogin {
+    pub name: String,
+    pub password: String,
+}
+
+#[post("/login")]
+pub async fn post_login(
+    body: web::Json<PostLogin>,
+    pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
+    config: Data<Config>,
+) -> HttpResponse {
+    // An unknown user and a wrong password must look identical to the
+    // caller, or this endpoint becomes a user-enumeration oracle.
+    match users::verify_password(pool.clone(), &body.name, &body.password).await {
+        Ok(true) => {}
+        Ok(false) => return error_log::INVALID_ARGUMENT::webmsg("Incorrect username or password."),
+        Err(e) => return e,
+    }
+    let user_id = match users::get_user_id(pool.clone(), &body.name).await {
+        Ok(id) => id,
+        Err(e) => return e,
+    };
+    let role = auth::role_for(&config, user_id);
+    let token = auth::sign_token(&config, user_id, role);
+    HttpResponse::Ok().body(serde_json::to_string_pretty(&serde_json::json!({ "token": token })).unwrap())
+}
+
 #[post("/jobs")]
 pub async fn post_job(
     body: web::Json<PostJob>,
+    auth_user: AuthUser,
     pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
     config: Data<Config>,
     prob_map: Data<HashMap<u32, config::Problem>>,
     ids: Data<Arc<Mutex<Ids>>>,
+    storage: Data<Arc<dyn Storage>>,
+    queue: Data<Arc<JobQueue>>,
 ) -> HttpResponse {
+    if body.user_id != auth_user.user_id && !auth_user.is_admin() {
+        return error_log::PERMISSION_DENIED::webmsg(
+            "Cannot submit a job on behalf of another user.",
+        );
+    }
     // check request
     if !config
         .languages
@@ -142,15 +186,15 @@ pub async fn post_job(
         if contest.submission_limit != 0 {
             let data = pool.lock().await.get().unwrap();
             let mut stmt;
-            match data.prepare(&format!("SELECT COUNT(*) FROM submission WHERE user_id = {} AND problem_id = {} AND contest_id = {};", body.user_id, body.problem_id, body.contest_id)) {
+            match data.prepare("SELECT COUNT(*) FROM submission WHERE user_id = ?1 AND problem_id = ?2 AND contest_id = ?3;") {
                 Ok(s) => stmt = s,
                 _ => { return error_log::EXTERNAL::webmsg("Database Error."); }
             };
             let mut submission_count: u32 = 0;
-            match stmt.exists([]) {
+            match stmt.exists(rusqlite::params![body.user_id, body.problem_id, body.contest_id]) {
                 Ok(true) => {
                     submission_count = stmt
-                        .query([])
+                        .query(rusqlite::params![body.user_id, body.problem_id, body.contest_id])
                         .unwrap()
                         .next()
                         .unwrap()
@@ -175,9 +219,38 @@ pub async fn post_job(
         }
     }
 
-    runner::start(body, pool, config, prob_map, ids.clone())
-        .await
-        .unwrap()
+    // Persist the source under its content-addressed key (dedup'd for
+    // identical submissions) and hand the runner the key, not the inline
+    // source, so it fetches the blob from `storage` before compiling.
+    let source_key = match storage.put(body.source_code.as_bytes()).await {
+        Ok(key) => key,
+        Err(_) => return error_log::EXTERNAL::webmsg("Storage Error."),
+    };
+    let runnable = RunnableJob {
+        source_key,
+        language: body.language.clone(),
+        user_id: body.user_id,
+        contest_id: body.contest_id,
+        problem_id: body.problem_id,
+    };
+
+    // `runner::start` only creates the job's row and its response body; the
+    // actual run goes through the durable queue, same as a rejudge.
+    let (job_id, ans) = match runner::start(
+        runnable.clone(),
+        pool.clone(),
+        config,
+        prob_map,
+        ids.clone(),
+        storage.clone(),
+    )
+    .await
+    {
+        Ok(created) => created,
+        Err(e) => return e,
+    };
+    queue.enqueue(pool.clone(), job_id, runnable).await;
+    ans
 }
 
 #[get("/jobs/{jobid}")]
@@ -195,15 +268,43 @@ pub async fn get_job_by_id(
     runner::get_job(pool, job_id).await
 }
 
+#[delete("/jobs/{jobid}")]
+pub async fn cancel_job_by_id(
+    path: web::Path<String>,
+    auth_user: AuthUser,
+    pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
+    queue: Data<Arc<JobQueue>>,
+) -> HttpResponse {
+    let job_id: u32 = match path.parse::<u32>() {
+        Ok(id) => id,
+        _ => {
+            return error_log::NOT_FOUND::webmsg(&format!("Job {} not found.", path));
+        }
+    };
+    let job = match runner::get_a_job(pool.clone(), job_id).await {
+        Ok(job) => job,
+        Err(e) => return e,
+    };
+    if job.get_post().user_id != auth_user.user_id && !auth_user.is_admin() {
+        return error_log::PERMISSION_DENIED::webmsg("Cannot cancel another user's job.");
+    }
+    match queue.cancel(pool.clone(), job_id).await {
+        Ok(()) => HttpResponse::Ok().finish(),
+        Err(e) => e,
+    }
+}
+
 #[get("/jobs")]
 pub async fn get_jobs(
     req: HttpRequest,
     pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
     ids: Data<Arc<Mutex<Ids>>>,
 ) -> HttpResponse {
-    let mut filter;
-    let reqstr = str::replace(req.query_string(), "+", "🜔");
-    println!("{:?}", reqstr);
+    let filter;
+    // Query strings are form-encoded, so `web::Query` decodes a bare `+` as
+    // a space; escape it to `%2B` first so a bare `+` (e.g.
+    // `language=C++`) stays a literal `+` instead of silently becoming a space.
+    let reqstr = req.query_string().replace('+', "%2B");
 
     match web::Query::<JobsFilter>::from_query(&reqstr) {
         Ok(flt) => filter = flt,
@@ -211,9 +312,6 @@ pub async fn get_jobs(
             return error_log::INVALID_ARGUMENT::webmsg("Invalid argument.");
         }
     };
-    if let Some(language) = &filter.language {
-        filter.language = Some(str::replace(language, "🜔", "+"));
-    }
 
     if let Some(str) = &filter.from {
         if let Err(_) = NaiveDateTime::parse_from_str(str, "%Y-%m-%dT%H:%M:%S%.3fZ") {
@@ -226,7 +324,12 @@ pub async fn get_jobs(
         }
     }
 
-    match runner::get_jobs(pool, filter.into_inner(), ids).await {
+    // Parameterize the equality filter fields up front — `language`/`user_name`
+    // are string-typed and must never be interpolated into the query `runner`
+    // builds from them. `from`/`to` are not in the clause; they are only
+    // checked against the timestamp format above.
+    let (where_clause, where_params) = query::jobs_filter_clause(&filter);
+    match runner::get_jobs(pool, filter.into_inner(), where_clause, where_params, ids).await {
         Ok(jobs) => HttpResponse::Ok().body(serde_json::to_string_pretty(&jobs).unwrap()),
         Err(e) => e,
     }
@@ -235,11 +338,15 @@ pub async fn get_jobs(
 #[put("/jobs/{jobid}")]
 pub async fn rejudge_job_by_id(
     path: web::Path<String>,
+    auth_user: AuthUser,
     pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
     ids: Data<Arc<Mutex<Ids>>>,
-    config: Data<Config>,
     prob_map: Data<HashMap<u32, config::Problem>>,
+    queue: Data<Arc<JobQueue>>,
 ) -> HttpResponse {
+    if !auth_user.is_admin() {
+        return error_log::PERMISSION_DENIED::webmsg("Admin role required.");
+    }
     println!("Rejuding...");
     let mut job_id: u32 = 0;
     match path.parse::<u32>() {
@@ -269,12 +376,24 @@ pub async fn rejudge_job_by_id(
             return e;
         }
     }
-    let _ = tokio::spawn(async move {
-        runner::run(post, pool.clone(), config.clone(), prob_map.clone(), job_id).await;
-    }); //.await;
+    queue.enqueue(pool.clone(), job_id, post).await;
     ans
 }
 
+#[get("/queue/stats")]
+pub async fn get_queue_stats(auth_user: AuthUser, queue: Data<Arc<JobQueue>>) -> HttpResponse {
+    if !auth_user.is_admin() {
+        return error_log::PERMISSION_DENIED::webmsg("Admin role required.");
+    }
+    HttpResponse::Ok().body(
+        serde_json::to_string_pretty(&serde_json::json!({
+            "depth": queue.depth(),
+            "in_flight": queue.in_flight(),
+        }))
+        .unwrap(),
+    )
+}
+
 #[post("/users")]
 pub async fn post_user(
     body: web::Json<PostUser>,
@@ -282,9 +401,13 @@ pub async fn post_user(
     ids: Data<Arc<Mutex<Ids>>>,
 ) -> HttpResponse {
     if let Some(id) = body.id {
-        users::update_user(pool, id, &body.name).await
+        users::update_user(pool, id, &body.name, body.password.as_deref()).await
     } else {
-        match users::create_user(pool, &body.name, ids.clone()).await {
+        let password = match &body.password {
+            Some(password) => password,
+            None => return error_log::INVALID_ARGUMENT::webmsg("Password is required."),
+        };
+        match users::create_user(pool, &body.name, password, ids.clone()).await {
             Ok(user) => HttpResponse::Ok().body(serde_json::to_string_pretty(&user).unwrap()),
             Err(e) => e,
         }
@@ -302,10 +425,14 @@ pub async fn get_users(pool: Data<Mutex<Pool<SqliteConnectionManager>>>) -> Http
 #[post("/contests")]
 pub async fn post_contest(
     body: web::Json<PostContest>,
+    auth_user: AuthUser,
     pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
     ids: Data<Arc<Mutex<Ids>>>,
     prob_map: Data<HashMap<u32, config::Problem>>,
 ) -> HttpResponse {
+    if !auth_user.is_admin() {
+        return error_log::PERMISSION_DENIED::webmsg("Admin role required.");
+    }
     for prob_id in &body.problem_ids {
         if !prob_map.contains_key(&prob_id) {
             // return message to be determined
@@ -0,0 +1,92 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+/// The lifecycle of a job: `Queued` jobs are waiting to run, `Running` jobs
+/// are executing, and all terminate in `Finished`, `Failed`, or `Canceled`.
+/// `Failed` is distinct from `Finished` — it means the runner never produced
+/// a result after exhausting its retries, not that judging completed
+/// successfully. Only the transitions enumerated in `is_valid_transition`
+/// are legal; everything else (e.g. canceling a `Finished` job) must be
+/// rejected by the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Canceled,
+}
+
+impl JobState {
+    pub fn is_valid_transition(self, next: JobState) -> bool {
+        matches!(
+            (self, next),
+            (JobState::Queued, JobState::Running)
+                | (JobState::Queued, JobState::Canceled)
+                | (JobState::Running, JobState::Queued)
+                | (JobState::Running, JobState::Finished)
+                | (JobState::Running, JobState::Failed)
+                | (JobState::Running, JobState::Canceled)
+        )
+    }
+}
+
+impl std::fmt::Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            JobState::Queued => "Queued",
+            JobState::Running => "Running",
+            JobState::Finished => "Finished",
+            JobState::Failed => "Failed",
+            JobState::Canceled => "Canceled",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for JobState {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Queued" => Ok(JobState::Queued),
+            "Running" => Ok(JobState::Running),
+            "Finished" => Ok(JobState::Finished),
+            "Failed" => Ok(JobState::Failed),
+            "Canceled" => Ok(JobState::Canceled),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A cooperative cancellation signal for a `Running` job. The runner is
+/// expected to poll `is_canceled` between test cases and stop early.
+#[derive(Clone)]
+pub struct CancellationToken {
+    canceled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken {
+            canceled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.canceled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_canceled(&self) -> bool {
+        self.canceled.load(Ordering::SeqCst)
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
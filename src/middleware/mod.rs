@@ -0,0 +1,3 @@
+pub mod rate_limit;
+
+pub use rate_limit::RateLimiter;
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::future::{ready, Ready};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::web::Data;
+use actix_web::{Error, HttpResponse};
+use futures_util::future::LocalBoxFuture;
+
+use crate::auth;
+use crate::config::Config;
+
+/// One bucket's capacity and refill rate (tokens/sec).
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub capacity: f64,
+    pub refill_per_sec: f64,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+const IDLE_BUCKET_TTL: Duration = Duration::from_secs(300);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Token-bucket rate limiting middleware, keyed by authenticated `user_id`
+/// (falling back to peer IP for anonymous requests). `overrides` lets
+/// specific `(method, path)` routes use a tighter bucket than `default`,
+/// e.g. `POST /jobs`. Idle buckets are pruned periodically to bound memory.
+///
+/// `Clone` is cheap (the bucket map is an `Arc`) and load-bearing: construct
+/// one `RateLimiter` with `new` *before* `HttpServer::new`, then clone it
+/// into the per-worker `App` factory. Calling `new` again inside the
+/// factory would give every worker thread its own bucket map, multiplying
+/// the effective limit by the worker count instead of sharing it.
+#[derive(Clone)]
+pub struct RateLimiter {
+    default: RateLimit,
+    overrides: HashMap<(&'static str, &'static str), RateLimit>,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config) -> Self {
+        let limiter = RateLimiter {
+            default: RateLimit {
+                capacity: config.rate_limit_capacity,
+                refill_per_sec: config.rate_limit_refill_per_sec,
+            },
+            overrides: HashMap::from([(
+                ("POST", "/jobs"),
+                RateLimit {
+                    capacity: config.job_rate_limit_capacity,
+                    refill_per_sec: config.job_rate_limit_refill_per_sec,
+                },
+            )]),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        };
+        limiter.spawn_pruner();
+        limiter
+    }
+
+    fn spawn_pruner(&self) {
+        let buckets = self.buckets.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(PRUNE_INTERVAL).await;
+                buckets
+                    .lock()
+                    .unwrap()
+                    .retain(|_, bucket| bucket.last_refill.elapsed() < IDLE_BUCKET_TTL);
+            }
+        });
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service,
+            default: self.default,
+            overrides: self.overrides.clone(),
+            buckets: self.buckets.clone(),
+        }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: S,
+    default: RateLimit,
+    overrides: HashMap<(&'static str, &'static str), RateLimit>,
+    buckets: Arc<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl<S> RateLimiterMiddleware<S> {
+    fn key_for(req: &ServiceRequest) -> String {
+        let authenticated = req
+            .headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .and_then(|token| {
+                req.app_data::<Data<Config>>()
+                    .and_then(|config| auth::verify_token(config, token).ok())
+            })
+            .map(|claims| format!("user:{}", claims.sub));
+
+        authenticated.unwrap_or_else(|| {
+            format!(
+                "ip:{}",
+                req.peer_addr()
+                    .map(|addr| addr.ip().to_string())
+                    .unwrap_or_default()
+            )
+        })
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let limit = self
+            .overrides
+            .get(&(req.method().as_str(), req.path()))
+            .copied()
+            .unwrap_or(self.default);
+        let key = Self::key_for(&req);
+
+        let retry_after_secs = {
+            let mut buckets = self.buckets.lock().unwrap();
+            let bucket = buckets.entry(key).or_insert_with(|| Bucket {
+                tokens: limit.capacity,
+                last_refill: Instant::now(),
+            });
+
+            let elapsed = bucket.last_refill.elapsed().as_secs_f64();
+            bucket.last_refill = Instant::now();
+            bucket.tokens = (bucket.tokens + elapsed * limit.refill_per_sec).min(limit.capacity);
+
+            if bucket.tokens >= 1.0 {
+                bucket.tokens -= 1.0;
+                None
+            } else {
+                let deficit = 1.0 - bucket.tokens;
+                Some((deficit / limit.refill_per_sec).ceil().max(1.0) as u64)
+            }
+        };
+
+        if let Some(retry_after_secs) = retry_after_secs {
+            let (req, _) = req.into_parts();
+            let response = HttpResponse::TooManyRequests()
+                .insert_header((
+                    HeaderName::from_static("retry-after"),
+                    HeaderValue::from_str(&retry_after_secs.to_string()).unwrap(),
+                ))
+                .finish()
+                .map_into_right_body();
+            return Box::pin(async move { Ok(ServiceResponse::new(req, response)) });
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(async move { fut.await.map(ServiceResponse::map_into_left_body) })
+    }
+}
@@ -0,0 +1,118 @@
+use rusqlite::types::ToSql;
+
+use crate::handler::jobs::JobsFilter;
+
+/// Assembles a parameterized SQL `WHERE` clause from a set of optional filter
+/// fields, so callers never interpolate user-controlled strings into a query.
+///
+/// Each `field` call appends `column = ?N` (and its bound value) only when
+/// the filter is present, letting `JobsFilter`-style structs build up a
+/// clause one field at a time instead of via `format!`.
+#[derive(Default)]
+pub struct QueryBuilder {
+    clauses: Vec<String>,
+    params: Vec<Box<dyn ToSql>>,
+}
+
+impl QueryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `column = ?` bound to `value`, if `value` is present.
+    pub fn field<T: ToSql + 'static>(mut self, column: &str, value: Option<T>) -> Self {
+        if let Some(value) = value {
+            self.clauses.push(format!("{} = ?{}", column, self.clauses.len() + 1));
+            self.params.push(Box::new(value));
+        }
+        self
+    }
+
+    /// Builds the trailing `WHERE ...` clause (empty if no filters were
+    /// added) and its bound parameters. Pass the parameters to rusqlite as
+    /// `rusqlite::params_from_iter(params.iter().map(|p| p.as_ref()))` — the
+    /// `&dyn ToSql` each `.as_ref()` yields already implements `ToSql` via
+    /// rusqlite's own blanket reference impl, so no impl is needed here.
+    pub fn build(self) -> (String, Vec<Box<dyn ToSql>>) {
+        if self.clauses.is_empty() {
+            (String::new(), self.params)
+        } else {
+            (format!("WHERE {}", self.clauses.join(" AND ")), self.params)
+        }
+    }
+}
+
+/// Builds the parameterized `WHERE` clause for a `GET /jobs` filter. Every
+/// equality field — including the string-typed `language`/`user_name`, the
+/// actual injection vectors — becomes a bound parameter instead of
+/// interpolated text. The `from`/`to` time bounds are not part of the clause.
+pub fn jobs_filter_clause(filter: &JobsFilter) -> (String, Vec<Box<dyn ToSql>>) {
+    QueryBuilder::new()
+        .field("user_id", filter.user_id)
+        .field("user_name", filter.user_name.clone())
+        .field("contest_id", filter.contest_id)
+        .field("problem_id", filter.problem_id)
+        .field("language", filter.language.clone())
+        .field("state", filter.state.map(|s| s.to_string()))
+        .field("result", filter.result.clone())
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_builder_has_no_where_clause() {
+        let (clause, params) = QueryBuilder::new().field::<u32>("user_id", None).build();
+        assert_eq!(clause, "");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn present_fields_are_bound_not_interpolated() {
+        let hostile = "'; DROP TABLE users;--".to_string();
+        let (clause, params) = QueryBuilder::new()
+            .field("user_name", Some(hostile.clone()))
+            .field::<u32>("contest_id", None)
+            .field("problem_id", Some(3u32))
+            .build();
+        assert_eq!(clause, "WHERE user_name = ?1 AND problem_id = ?2");
+        assert!(!clause.contains("DROP TABLE"));
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn hostile_user_name_is_bound_safely_against_a_real_connection() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE users (id INTEGER, user_name TEXT);",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO users (id, user_name) VALUES (1, 'alice');",
+            [],
+        )
+        .unwrap();
+
+        let hostile = "'; DROP TABLE users;--".to_string();
+        let (clause, params) = QueryBuilder::new().field("user_name", Some(hostile)).build();
+        let sql = format!("SELECT COUNT(*) FROM users {};", clause);
+        let count: u32 = conn
+            .query_row(
+                &sql,
+                rusqlite::params_from_iter(params.iter().map(|p| p.as_ref())),
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        assert_eq!(count, 0);
+        // The `users` table must have survived — a naive `format!` of the
+        // hostile string into the query would have dropped it instead.
+        let remaining: u32 = conn
+            .query_row("SELECT COUNT(*) FROM users;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 1);
+    }
+}
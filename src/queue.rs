@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::Duration;
+
+use actix_web::web::Data;
+use actix_web::HttpResponse;
+use r2d2::Pool;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::config::{self, Config};
+use crate::error_log;
+use crate::job_state::{CancellationToken, JobState};
+use crate::runner;
+
+const MAX_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+
+/// What the runner needs to compile and judge a submission: the same
+/// metadata as `PostJob`, but `source_key` in place of inline source so the
+/// runner fetches the blob from `Storage` instead of reading it out of the
+/// request body (which is how it stays durable across retries/recovery too).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunnableJob {
+    pub source_key: String,
+    pub language: String,
+    pub user_id: u32,
+    pub contest_id: u32,
+    pub problem_id: u32,
+}
+
+#[derive(Debug, Clone)]
+struct QueuedJob {
+    job_id: u32,
+    attempt: u32,
+    post: RunnableJob,
+}
+
+/// A durable, retrying replacement for bare `tokio::spawn`s around job runs.
+/// Jobs are persisted to the `job_queue` table on enqueue, and a fixed pool
+/// of `config.queue_workers` tasks pull ready work, run it, and requeue on
+/// panic with capped exponential backoff. Each in-flight job gets a
+/// `CancellationToken` so `DELETE /jobs/{jobid}` can signal the runner to
+/// abort between test cases.
+pub struct JobQueue {
+    sender: mpsc::UnboundedSender<QueuedJob>,
+    depth: Arc<AtomicUsize>,
+    in_flight: Arc<AtomicUsize>,
+    cancellations: Arc<StdMutex<HashMap<u32, CancellationToken>>>,
+}
+
+impl JobQueue {
+    /// Spawns `workers` worker tasks pulling from an internal channel.
+    pub fn start(
+        workers: usize,
+        pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
+        config: Data<Config>,
+        prob_map: Data<HashMap<u32, config::Problem>>,
+    ) -> Arc<JobQueue> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let depth = Arc::new(AtomicUsize::new(0));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let cancellations = Arc::new(StdMutex::new(HashMap::new()));
+
+        for _ in 0..workers {
+            let receiver = receiver.clone();
+            let pool = pool.clone();
+            let config = config.clone();
+            let prob_map = prob_map.clone();
+            let depth = depth.clone();
+            let in_flight = in_flight.clone();
+            let cancellations = cancellations.clone();
+            let queue_sender = sender.clone();
+            tokio::spawn(async move {
+                loop {
+                    let job = receiver.lock().await.recv().await;
+                    let job = match job {
+                        Some(job) => job,
+                        None => break,
+                    };
+                    depth.fetch_sub(1, Ordering::SeqCst);
+
+                    if read_status(pool.clone(), job.job_id).await == Some(JobState::Canceled) {
+                        continue;
+                    }
+
+                    in_flight.fetch_add(1, Ordering::SeqCst);
+                    set_status(pool.clone(), job.job_id, JobState::Running).await;
+                    let token = CancellationToken::new();
+                    cancellations
+                        .lock()
+                        .unwrap()
+                        .insert(job.job_id, token.clone());
+
+                    let handle = tokio::spawn(runner::run(
+                        job.post.clone(),
+                        pool.clone(),
+                        config.clone(),
+                        prob_map.clone(),
+                        job.job_id,
+                        token.clone(),
+                    ));
+                    let result = handle.await;
+                    cancellations.lock().unwrap().remove(&job.job_id);
+
+                    if token.is_canceled() {
+                        // Already marked Canceled by the DELETE handler.
+                    } else if result.is_err() {
+                        requeue_or_giveup(&queue_sender, pool.clone(), depth.clone(), job).await;
+                    } else {
+                        set_status(pool.clone(), job.job_id, JobState::Finished).await;
+                    }
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                }
+            });
+        }
+
+        Arc::new(JobQueue {
+            sender,
+            depth,
+            in_flight,
+            cancellations,
+        })
+    }
+
+    /// Enqueues `job_id` for (re)execution, persisting it to the `job_queue`
+    /// table so a crash mid-run can be recovered on the next startup.
+    pub async fn enqueue(
+        &self,
+        pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
+        job_id: u32,
+        post: RunnableJob,
+    ) {
+        {
+            let post_json = serde_json::to_string(&post).expect("Failed to serialize job.");
+            let data = pool.lock().await.get().unwrap();
+            let _ = data.execute(
+                "INSERT INTO job_queue (job_id, attempt, status, post) VALUES (?1, 0, ?2, ?3)
+                 ON CONFLICT(job_id) DO UPDATE SET attempt = 0, status = ?2, post = ?3;",
+                params![job_id, JobState::Queued.to_string(), post_json],
+            );
+        }
+        self.depth.fetch_add(1, Ordering::SeqCst);
+        let _ = self.sender.send(QueuedJob {
+            job_id,
+            attempt: 0,
+            post,
+        });
+    }
+
+    /// Cancels `job_id`: a `Queued` job is marked `Canceled` directly (the
+    /// worker will skip it when its turn comes), a `Running` job has its
+    /// cancellation token signaled. Any other current state is rejected.
+    pub async fn cancel(
+        &self,
+        pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
+        job_id: u32,
+    ) -> Result<(), HttpResponse> {
+        let current = match read_status(pool.clone(), job_id).await {
+            Some(state) => state,
+            // The job itself may still exist (callers check that separately
+            // before reaching here); it just never entered the queue.
+            None => {
+                return Err(error_log::NOT_FOUND::webmsg(&format!(
+                    "Job {} has no queue entry.",
+                    job_id
+                )))
+            }
+        };
+        if !current.is_valid_transition(JobState::Canceled) {
+            return Err(error_log::INVALID_ARGUMENT::webmsg(&format!(
+                "Cannot cancel a job in state {}.",
+                current
+            )));
+        }
+        set_status(pool.clone(), job_id, JobState::Canceled).await;
+        if let Some(token) = self.cancellations.lock().unwrap().get(&job_id) {
+            token.cancel();
+        }
+        Ok(())
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth.load(Ordering::SeqCst)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+}
+
+async fn read_status(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, job_id: u32) -> Option<JobState> {
+    let data = pool.lock().await.get().unwrap();
+    let mut stmt = data
+        .prepare("SELECT status FROM job_queue WHERE job_id = ?1;")
+        .ok()?;
+    let status: String = stmt.query_row(params![job_id], |row| row.get(0)).ok()?;
+    status.parse().ok()
+}
+
+async fn set_status(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, job_id: u32, status: JobState) {
+    {
+        let data = pool.lock().await.get().unwrap();
+        let _ = data.execute(
+            "UPDATE job_queue SET status = ?1 WHERE job_id = ?2;",
+            params![status.to_string(), job_id],
+        );
+    }
+    // `job_queue` is this module's own bookkeeping table; GET /jobs and
+    // GET /jobs/{id} read the job row through `runner` instead, so every
+    // terminal/cancellation transition has to be mirrored there too or the
+    // state a client can observe goes stale the moment a job leaves `Queued`.
+    runner::set_job_state(pool, job_id, status).await;
+}
+
+async fn requeue_or_giveup(
+    sender: &mpsc::UnboundedSender<QueuedJob>,
+    pool: Data<Mutex<Pool<SqliteConnectionManager>>>,
+    depth: Arc<AtomicUsize>,
+    mut job: QueuedJob,
+) {
+    job.attempt += 1;
+    if job.attempt >= MAX_ATTEMPTS {
+        // Exhausted retries without a result — this is a failure, not a
+        // successful completion, so it must not be reported as `Finished`.
+        set_status(pool.clone(), job.job_id, JobState::Failed).await;
+        return;
+    }
+    {
+        let data = pool.lock().await.get().unwrap();
+        let _ = data.execute(
+            "UPDATE job_queue SET attempt = ?1, status = ?2 WHERE job_id = ?3;",
+            params![job.attempt, JobState::Queued.to_string(), job.job_id],
+        );
+    }
+    let backoff = BASE_BACKOFF * 2u32.pow(job.attempt.min(6));
+    tokio::time::sleep(backoff).await;
+    depth.fetch_add(1, Ordering::SeqCst);
+    let _ = sender.send(job);
+}
+
+/// Re-enqueues any job left in the `Running` state, for self-healing after a
+/// crash mid-run. Call once at startup, before accepting traffic.
+pub async fn recover_running_jobs(queue: &JobQueue, pool: Data<Mutex<Pool<SqliteConnectionManager>>>) {
+    let rows: Vec<(u32, String)> = {
+        let data = pool.lock().await.get().unwrap();
+        let mut stmt = match data.prepare("SELECT job_id, post FROM job_queue WHERE status = ?1;") {
+            Ok(s) => s,
+            _ => return,
+        };
+        let rows = stmt
+            .query_map(params![JobState::Running.to_string()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        rows.filter_map(|r| r.ok()).collect()
+    };
+    for (job_id, post_json) in rows {
+        if let Ok(post) = serde_json::from_str::<RunnableJob>(&post_json) {
+            queue.enqueue(pool.clone(), job_id, post).await;
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+use crate::config::Config;
+
+/// Pluggable content-addressed blob storage for submission source code and
+/// problem test data. Blobs are keyed by the SHA-256 hex digest of their
+/// bytes, so identical submissions automatically dedup onto the same key
+/// instead of being stored (and compiled) twice.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Stores `bytes` under its content key, returning that key. A no-op if
+    /// the key is already present.
+    async fn put(&self, bytes: &[u8]) -> std::io::Result<String>;
+
+    async fn get(&self, key: &str) -> std::io::Result<Vec<u8>>;
+}
+
+pub fn content_key(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Local-filesystem backend. This is also what tests use, so they don't need
+/// a network, and it remains the fallback when no S3 endpoint is configured.
+pub struct LocalStorage {
+    base_dir: std::path::PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(base_dir: impl Into<std::path::PathBuf>) -> Self {
+        LocalStorage {
+            base_dir: base_dir.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn put(&self, bytes: &[u8]) -> std::io::Result<String> {
+        let key = content_key(bytes);
+        let path = self.base_dir.join(&key);
+        if !path.exists() {
+            tokio::fs::create_dir_all(&self.base_dir).await?;
+            tokio::fs::write(&path, bytes).await?;
+        }
+        Ok(key)
+    }
+
+    async fn get(&self, key: &str) -> std::io::Result<Vec<u8>> {
+        tokio::fs::read(self.base_dir.join(key)).await
+    }
+}
+
+/// S3-compatible backend (endpoint, bucket, access/secret keys all read from
+/// `Config`), used in production so submissions and test data can scale
+/// horizontally instead of bloating the SQLite file.
+pub struct S3Storage {
+    bucket: String,
+    client: aws_sdk_s3::Client,
+}
+
+impl S3Storage {
+    pub async fn from_config(config: &Config) -> Self {
+        let credentials = aws_sdk_s3::config::Credentials::new(
+            &config.s3_access_key,
+            &config.s3_secret_key,
+            None,
+            None,
+            "mroj-config",
+        );
+        let shared_config = aws_config::from_env()
+            .endpoint_url(&config.s3_endpoint)
+            .credentials_provider(credentials)
+            .load()
+            .await;
+        // Path-style addressing (`endpoint/bucket/key`) is what MinIO and
+        // most other S3-compatible endpoints expect; virtual-hosted style
+        // (the SDK's default) only resolves against real AWS DNS.
+        let s3_config = aws_sdk_s3::config::Builder::from(&shared_config)
+            .force_path_style(true)
+            .build();
+        S3Storage {
+            bucket: config.s3_bucket.clone(),
+            client: aws_sdk_s3::Client::from_conf(s3_config),
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for S3Storage {
+    async fn put(&self, bytes: &[u8]) -> std::io::Result<String> {
+        let key = content_key(bytes);
+        let exists = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(&key)
+            .send()
+            .await
+            .is_ok();
+        if !exists {
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(&key)
+                .body(bytes.to_vec().into())
+                .send()
+                .await
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        }
+        Ok(key)
+    }
+
+    async fn get(&self, key: &str) -> std::io::Result<Vec<u8>> {
+        let output = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        let data = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        Ok(data.into_bytes().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn identical_submissions_dedup_onto_the_same_key() {
+        let dir = std::env::temp_dir().join(format!("mroj-storage-test-{}", std::process::id()));
+        let storage = LocalStorage::new(&dir);
+
+        let key_a = storage.put(b"print(1)").await.unwrap();
+        let key_b = storage.put(b"print(1)").await.unwrap();
+        assert_eq!(key_a, key_b);
+
+        let fetched = storage.get(&key_a).await.unwrap();
+        assert_eq!(fetched, b"print(1)");
+
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+    }
+}
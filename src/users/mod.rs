@@ -1,5 +1,7 @@
 use actix_web::web::Data;
 use actix_web::{delete, get, post, put, web, Responder, HttpResponse, HttpResponseBuilder, HttpRequest};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
 use serde::{Deserialize, Serialize};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
@@ -13,6 +15,8 @@ use crate::config::{self, Config, Ids};
 use crate::runner::{self, SerdeJob};
 
 
+// `password_hash` lives only in the `users` table; it is never read into this
+// struct, so it can't leak through a serialized response.
 #[derive(Deserialize, Serialize, Clone, Default, Debug)]
 pub struct SerdeUser {
     id: u32,
@@ -73,7 +77,42 @@ pub async fn user_exists (pool: Data<Mutex<Pool<SqliteConnectionManager>>>, user
     stmt.exists(&[(":name", user_name)]).unwrap()
 }
 
-pub async fn update_user(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, user_id: u32, user_name: &str) -> HttpResponse {
+/// Hashes `password` with Argon2id and a fresh random salt, returning the PHC
+/// string (algorithm + salt + params travel with the hash).
+fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Failed to hash password.")
+        .to_string()
+}
+
+/// Checks `password` against the stored PHC hash for `name` in constant
+/// time. Returns `Ok(false)` for both an unknown `name` and a wrong
+/// `password` — the caller must not distinguish the two, or a login
+/// endpoint becomes a user-enumeration oracle.
+pub async fn verify_password(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, name: &str, password: &str) -> Result<bool, HttpResponse> {
+    let data = pool.lock().await.get().unwrap();
+    let mut stmt;
+    match data.prepare("SELECT password_hash FROM users WHERE name = :name;") {
+        Ok(s) => stmt = s,
+        _ => { return Err( error_log::EXTERNAL::webmsg("Database Error.")); }
+    }
+    if !stmt.exists(&[(":name", name)]).unwrap() {
+        return Ok(false);
+    }
+    let hash: String = match stmt.query_row(&[(":name", name)], |row| row.get(0)) {
+        Ok(h) => h,
+        _ => { return Err( error_log::EXTERNAL::webmsg("Database Error.")); }
+    };
+    let parsed = match PasswordHash::new(&hash) {
+        Ok(p) => p,
+        _ => { return Err( error_log::EXTERNAL::webmsg("Database Error.")); }
+    };
+    Ok(Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+}
+
+pub async fn update_user(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, user_id: u32, user_name: &str, password: Option<&str>) -> HttpResponse {
     println!("Users: Updating User...");
     let mut user: SerdeUser;
     match get_user(pool.clone(), user_id).await {
@@ -89,11 +128,16 @@ pub async fn update_user(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, user_
             let _ = data.execute("UPDATE users SET name = ?1 WHERE id = ?2;", params![user_name.to_string(), user_id as i32]);
         }
     }
+    if let Some(password) = password {
+        let password_hash = hash_password(password);
+        let data = pool.lock().await.get().unwrap();
+        let _ = data.execute("UPDATE users SET password_hash = ?1 WHERE id = ?2;", params![password_hash, user_id as i32]);
+    }
     user.name = user_name.to_string();
     HttpResponse::Ok().body(serde_json::to_string_pretty(&user).unwrap())
 }
 
-pub async fn create_user(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, user_name: &str, ids: Data<Arc<Mutex<Ids>>>) -> Result<(SerdeUser, u32), HttpResponse> {
+pub async fn create_user(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, user_name: &str, password: &str, ids: Data<Arc<Mutex<Ids>>>) -> Result<(SerdeUser, u32), HttpResponse> {
     println!("Users: Creating User...");
 
     let user_id: u32 = ids.lock().await.usersid;
@@ -103,8 +147,9 @@ pub async fn create_user(pool: Data<Mutex<Pool<SqliteConnectionManager>>>, user_
     if user_exists(pool.clone(), user_name).await {
         return Err( error_log::INVALID_ARGUMENT::webmsg(&format!("User name '{}' already exists.", user_name)));
     } else {
+        let password_hash = hash_password(password);
         let data = pool.lock().await.get().unwrap();
-        if let Err(e) = data.execute("INSERT INTO users (id, name) VALUES (?1, ?2);", params![user_id, user_name]) {
+        if let Err(e) = data.execute("INSERT INTO users (id, name, password_hash) VALUES (?1, ?2, ?3);", params![user_id, user_name, password_hash]) {
             return Err( error_log::EXTERNAL::webmsg("Database Error."));
         }
     }